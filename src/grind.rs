@@ -0,0 +1,227 @@
+use crate::password_entropy::SYMBOLS_SPACE;
+use crate::password_generator::{DIGITS, LOWERCASE, UPPERCASE};
+use crate::BoxResult;
+
+#[derive(Debug, Clone, Default)]
+pub struct GrindConstraints {
+    pub starts: Vec<String>,
+    pub ends: Vec<String>,
+}
+
+impl GrindConstraints {
+    pub fn matches(&self, word: &[u8]) -> bool {
+        let starts_ok = self.starts.is_empty()
+            || self
+                .starts
+                .iter()
+                .any(|prefix| word.starts_with(prefix.as_bytes()));
+        let ends_ok = self.ends.is_empty()
+            || self
+                .ends
+                .iter()
+                .any(|suffix| word.ends_with(suffix.as_bytes()));
+        starts_ok && ends_ok
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaskSlot {
+    alphabet: Vec<u8>,
+}
+
+impl MaskSlot {
+    fn size(&self) -> u128 {
+        self.alphabet.len() as u128
+    }
+
+    fn byte_at(&self, index: usize) -> u8 {
+        self.alphabet[index]
+    }
+}
+
+/// `?w1`-`?w9` wordlist tokens aren't indexable here and are rejected
+pub fn parse_mask_slots(mask: &str, custom_charsets: &[&str]) -> BoxResult<Vec<MaskSlot>> {
+    let bytes = mask.as_bytes();
+    let mut slots = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'?' {
+            slots.push(MaskSlot {
+                alphabet: vec![bytes[i]],
+            });
+            i += 1;
+            continue;
+        }
+
+        let token = *bytes
+            .get(i + 1)
+            .ok_or_else(|| format!("mask ends with a dangling '?' at position {}", i))?;
+
+        let (alphabet, consumed): (Vec<u8>, usize) = match token {
+            b'?' => (vec![b'?'], 2),
+            b'd' => (DIGITS.to_vec(), 2),
+            b'l' => (LOWERCASE.to_vec(), 2),
+            b'u' => (UPPERCASE.to_vec(), 2),
+            b's' => (SYMBOLS_SPACE.to_vec(), 2),
+            b'a' => {
+                let capacity =
+                    UPPERCASE.len() + LOWERCASE.len() + DIGITS.len() + SYMBOLS_SPACE.len();
+                let mut all = Vec::with_capacity(capacity);
+                all.extend_from_slice(UPPERCASE);
+                all.extend_from_slice(LOWERCASE);
+                all.extend_from_slice(DIGITS);
+                all.extend_from_slice(SYMBOLS_SPACE);
+                (all, 2)
+            }
+            b'b' => ((0u16..=255).map(|v| v as u8).collect(), 2),
+            b'w' => bail!(
+                "grind mode doesn't support wordlist mask tokens (?w1-?w9) at position {}",
+                i
+            ),
+            digit if digit.is_ascii_digit() && digit != b'0' => {
+                let n = (digit - b'0') as usize;
+                let charset = custom_charsets.get(n - 1).ok_or_else(|| {
+                    format!(
+                        "mask references ?{} but only {} --custom-charset value(s) were given",
+                        n,
+                        custom_charsets.len()
+                    )
+                })?;
+                (charset.as_bytes().to_vec(), 2)
+            }
+            other => bail!("unsupported mask token '?{}' at position {}", other as char, i),
+        };
+
+        slots.push(MaskSlot { alphabet });
+        i += consumed;
+    }
+    Ok(slots)
+}
+
+pub fn total_combinations(slots: &[MaskSlot]) -> BoxResult<u128> {
+    slots.iter().try_fold(1u128, |total, slot| {
+        total
+            .checked_mul(slot.size())
+            .ok_or_else(|| "mask keyspace is too large to index (overflows u128)".into())
+    })
+}
+
+pub fn decode(slots: &[MaskSlot], index: u128) -> Vec<u8> {
+    let mut digits = vec![0usize; slots.len()];
+    let mut remainder = index;
+    for (slot, digit) in slots.iter().zip(digits.iter_mut()).rev() {
+        let base = slot.size();
+        *digit = (remainder % base) as usize;
+        remainder /= base;
+    }
+
+    slots
+        .iter()
+        .zip(digits.iter())
+        .map(|(slot, &digit)| slot.byte_at(digit))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrindRange {
+    pub start: u128,
+    pub end: u128,
+}
+
+pub fn split_ranges(total: u128, workers: u64) -> Vec<GrindRange> {
+    let workers = u128::from(workers.max(1));
+    let chunk = (total + workers - 1) / workers;
+    if chunk == 0 {
+        return vec![];
+    }
+    (0..workers)
+        .map(|i| GrindRange {
+            start: (i * chunk).min(total),
+            end: ((i + 1) * chunk).min(total),
+        })
+        .filter(|r| r.start < r.end)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, parse_mask_slots, split_ranges, total_combinations, GrindConstraints, GrindRange};
+
+    #[test]
+    fn test_grind_constraints_empty_matches_everything() {
+        assert!(GrindConstraints::default().matches(b"anything"));
+    }
+
+    #[test]
+    fn test_grind_constraints_prefix_and_suffix() {
+        let constraints = GrindConstraints {
+            starts: vec!["ab".to_owned()],
+            ends: vec!["99".to_owned()],
+        };
+        assert!(constraints.matches(b"ab1299"));
+        assert!(!constraints.matches(b"xx1299"));
+        assert!(!constraints.matches(b"ab1200"));
+    }
+
+    #[test]
+    fn test_split_ranges_covers_total_without_overlap() {
+        let ranges = split_ranges(10, 3);
+        assert_eq!(
+            ranges,
+            vec![
+                GrindRange { start: 0, end: 4 },
+                GrindRange { start: 4, end: 8 },
+                GrindRange { start: 8, end: 10 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_ranges_more_workers_than_items() {
+        let ranges = split_ranges(2, 5);
+        assert_eq!(
+            ranges,
+            vec![GrindRange { start: 0, end: 1 }, GrindRange { start: 1, end: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_decode_covers_full_keyspace_in_order() {
+        let slots = parse_mask_slots("?d?d", &[]).unwrap();
+        assert_eq!(total_combinations(&slots).unwrap(), 100);
+        assert_eq!(decode(&slots, 0), b"00");
+        assert_eq!(decode(&slots, 1), b"01");
+        assert_eq!(decode(&slots, 99), b"99");
+    }
+
+    #[test]
+    fn test_decode_seeks_directly_to_an_arbitrary_offset() {
+        let slots = parse_mask_slots("?u?l?l?d?d", &[]).unwrap();
+        let total = total_combinations(&slots).unwrap();
+        let last_index = total - 1;
+        assert_eq!(decode(&slots, last_index), b"Zzz99");
+    }
+
+    #[test]
+    fn test_parse_mask_slots_literal_and_custom_charset() {
+        let slots = parse_mask_slots("ab?1?d", &["xy"]).unwrap();
+        assert_eq!(total_combinations(&slots).unwrap(), 20);
+        assert_eq!(decode(&slots, 0), b"abx0");
+    }
+
+    #[test]
+    fn test_parse_mask_slots_literal_question_mark_escape() {
+        let slots = parse_mask_slots("a??b", &[]).unwrap();
+        assert_eq!(decode(&slots, 0), b"a?b");
+    }
+
+    #[test]
+    fn test_parse_mask_slots_rejects_wordlist_tokens() {
+        assert!(parse_mask_slots("?w1", &[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_mask_slots_rejects_unknown_custom_charset_index() {
+        assert!(parse_mask_slots("?2", &["only-one"]).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use rand::rngs::OsRng;
+use rand::Rng;
+
+use crate::wordlists::OrderedWordlist;
+use crate::BoxResult;
+
+/// rolls needed to address one word in a 7776-entry (6^5) diceware list
+pub(crate) const DICE_ROLLS_PER_WORD: usize = 5;
+const DICE_SIDES: usize = 6;
+
+#[derive(Debug, PartialEq)]
+pub struct Passphrase {
+    pub words: Vec<String>,
+    pub entropy_bits: f64,
+}
+
+impl Passphrase {
+    pub fn join(&self, separator: &str) -> String {
+        self.words.join(separator)
+    }
+}
+
+pub fn generate_passphrase(wordlist: &OrderedWordlist, word_count: usize) -> Passphrase {
+    let mut rng = OsRng;
+    let words = (0..word_count)
+        .map(|_| {
+            let idx = rng.gen_range(0, wordlist.len());
+            String::from_utf8_lossy(&wordlist[idx]).to_string()
+        })
+        .collect();
+
+    Passphrase {
+        words,
+        entropy_bits: word_count as f64 * (wordlist.len() as f64).log2(),
+    }
+}
+
+pub fn passphrase_from_dice_rolls(wordlist: &OrderedWordlist, rolls: &[u8]) -> BoxResult<Passphrase> {
+    if rolls.is_empty() || rolls.len() % DICE_ROLLS_PER_WORD != 0 {
+        bail!(
+            "dice rolls must come in groups of {}, got {}",
+            DICE_ROLLS_PER_WORD,
+            rolls.len()
+        );
+    }
+
+    let words = rolls
+        .chunks(DICE_ROLLS_PER_WORD)
+        .map(|group| {
+            let mut idx = 0usize;
+            for &roll in group {
+                if roll < 1 || roll as usize > DICE_SIDES {
+                    bail!("dice roll out of range 1..={}: {}", DICE_SIDES, roll);
+                }
+                idx = idx * DICE_SIDES + (roll as usize - 1);
+            }
+            if idx >= wordlist.len() {
+                bail!("dice roll index {} is out of wordlist bounds", idx);
+            }
+            Ok(String::from_utf8_lossy(&wordlist[idx]).to_string())
+        })
+        .collect::<BoxResult<Vec<_>>>()?;
+
+    let word_count = words.len();
+    Ok(Passphrase {
+        words,
+        entropy_bits: word_count as f64 * (wordlist.len() as f64).log2(),
+    })
+}
+
+pub fn parse_dice_rolls(input: &str) -> BoxResult<Vec<u8>> {
+    input
+        .split_whitespace()
+        .flat_map(|tok| tok.bytes())
+        .map(|b| {
+            if !b.is_ascii_digit() {
+                bail!("invalid dice roll character: {}", b as char);
+            }
+            Ok(b - b'0')
+        })
+        .collect()
+}
+
+/// minimum word count such that `word_count * log2(wordlist_len) >= target_bits`
+pub fn words_for_target_entropy(wordlist_len: usize, target_bits: f64) -> usize {
+    let bits_per_word = (wordlist_len as f64).log2();
+    (target_bits / bits_per_word).ceil() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::passphrase::{
+        parse_dice_rolls, passphrase_from_dice_rolls, words_for_target_entropy,
+    };
+    use crate::wordlists::OrderedWordlist;
+    use std::path;
+
+    #[test]
+    fn test_passphrase_from_dice_rolls() {
+        let wordlist = OrderedWordlist::from_file(&wordlist_fname("wordlist1.txt")).unwrap();
+        let rolls = parse_dice_rolls("11111").unwrap();
+        let passphrase = passphrase_from_dice_rolls(&wordlist, &rolls).unwrap();
+        assert_eq!(passphrase.words.len(), 1);
+        assert_eq!(passphrase.words[0], String::from_utf8_lossy(&wordlist[0]));
+    }
+
+    #[test]
+    fn test_passphrase_from_dice_rolls_bad_groups() {
+        let wordlist = OrderedWordlist::from_file(&wordlist_fname("wordlist1.txt")).unwrap();
+        let rolls = parse_dice_rolls("1111").unwrap();
+        assert!(passphrase_from_dice_rolls(&wordlist, &rolls).is_err());
+    }
+
+    #[test]
+    fn test_parse_dice_rolls_rejects_non_digits() {
+        assert!(parse_dice_rolls("1 2 3 4 x").is_err());
+    }
+
+    #[test]
+    fn test_words_for_target_entropy() {
+        // 6^5 = 7776 entries, ~12.925 bits/word
+        assert_eq!(words_for_target_entropy(7776, 80f64), 7);
+    }
+
+    fn wordlist_fname(fname: &str) -> String {
+        let mut d = path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.extend(vec!["test-resources", fname]);
+        d.to_str().unwrap().to_owned()
+    }
+}
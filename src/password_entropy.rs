@@ -2,44 +2,127 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use flate2::read::GzDecoder;
 use ordered_float::OrderedFloat;
 use pathfinding::astar;
 use simple_error::SimpleError;
 
 use crate::BoxResult;
 
-const SYMBOLS_SPACE: &[u8; 32] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
-
-pub fn compute_password_entropy(pwd: &str) -> BoxResult<(f64, Vec<String>)> {
-    // load vocab file
-    let word2rank = load_vocab("/home/samar/dev/cracken/vocab.txt")?;
-    let raw_pwd = pwd.as_bytes();
-    let amatch = astar(
-        &0usize,
-        |&n| {
-            (n..=raw_pwd.len())
-                .rev()
-                .filter_map(|i| {
-                    word2rank
-                        .get(&raw_pwd[n..i])
-                        .map(|rank| (i, OrderedFloat::<f64>((*rank as f64).log2())))
-                })
-                .collect::<Vec<_>>()
-        },
-        |_| OrderedFloat::<f64>(0f64),
-        |&n| n == raw_pwd.len(),
-    );
-    let (best_path, entropy) =
-        amatch.ok_or_else(|| SimpleError::new("bad characters in password"))?;
-
-    let mut best_split = Vec::with_capacity(best_path.len() - 1);
-    let mut prev = 0usize;
-    for i in best_path.into_iter().skip(1) {
-        let word_i = &raw_pwd[prev..i];
-        best_split.push(String::from_utf8_lossy(word_i).to_string());
-        prev = i;
-    }
-    Ok((entropy.into_inner(), best_split))
+const DEFAULT_VOCAB: &[u8] = include_bytes!("../assets/vocab.txt");
+
+pub(crate) const SYMBOLS_SPACE: &[u8; 32] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+const SEQUENCE_MIN_LEN: usize = 3;
+const REPEAT_MIN_LEN: usize = 3;
+
+const LEET_SUBSTITUTIONS: &[(u8, &[u8])] = &[
+    (b'4', &[b'a']),
+    (b'@', &[b'a']),
+    (b'8', &[b'b']),
+    (b'3', &[b'e']),
+    (b'9', &[b'g']),
+    (b'1', &[b'i', b'l']),
+    (b'!', &[b'i']),
+    (b'0', &[b'o']),
+    (b'$', &[b's']),
+    (b'5', &[b's']),
+    (b'7', &[b't']),
+    (b'+', &[b't']),
+];
+
+#[derive(Debug)]
+pub struct EntropyModel {
+    word2rank: HashMap<Vec<u8>, usize>,
+}
+
+impl EntropyModel {
+    pub fn from_files(fnames: &[&str]) -> BoxResult<EntropyModel> {
+        let mut word2rank: HashMap<Vec<u8>, usize> = HashMap::new();
+        for fname in fnames {
+            merge_vocab(open_vocab_reader(fname)?, &mut word2rank)?;
+        }
+        fill_missing_single_bytes(&mut word2rank);
+        word2rank.shrink_to_fit();
+        Ok(EntropyModel { word2rank })
+    }
+
+    pub fn from_default() -> BoxResult<EntropyModel> {
+        let mut word2rank: HashMap<Vec<u8>, usize> = HashMap::new();
+        merge_vocab(DEFAULT_VOCAB, &mut word2rank)?;
+        fill_missing_single_bytes(&mut word2rank);
+        word2rank.shrink_to_fit();
+        Ok(EntropyModel { word2rank })
+    }
+
+    pub fn score(&self, pwd: &str) -> BoxResult<(f64, Vec<String>)> {
+        let word2rank = &self.word2rank;
+        let raw_pwd = pwd.as_bytes();
+        let amatch = astar(
+            &0usize,
+            |&n| {
+                let mut edges: Vec<_> = (n..=raw_pwd.len())
+                    .rev()
+                    .filter_map(|i| {
+                        let slice = &raw_pwd[n..i];
+                        if let Some(rank) = word2rank.get(slice) {
+                            return Some((i, OrderedFloat::<f64>((*rank as f64).log2())));
+                        }
+                        let (canonical, surcharge) = deleet(slice);
+                        word2rank.get(canonical.as_slice()).map(|rank| {
+                            (i, OrderedFloat::<f64>((*rank as f64).log2() + surcharge))
+                        })
+                    })
+                    .collect();
+
+                if let Some((len, class)) = sequence_run_len(&raw_pwd[n..]) {
+                    let cost = (class as f64).log2() + (len as f64).log2() + 1f64;
+                    edges.push((n + len, OrderedFloat(cost)));
+                }
+                if let Some((len, class)) = repeat_run_len(&raw_pwd[n..]) {
+                    let cost = (class as f64).log2() + (len as f64).log2();
+                    edges.push((n + len, OrderedFloat(cost)));
+                }
+
+                edges
+            },
+            |_| OrderedFloat::<f64>(0f64),
+            |&n| n == raw_pwd.len(),
+        );
+        let (best_path, entropy) =
+            amatch.ok_or_else(|| SimpleError::new("bad characters in password"))?;
+
+        let mut best_split = Vec::with_capacity(best_path.len() - 1);
+        let mut prev = 0usize;
+        for i in best_path.into_iter().skip(1) {
+            let word_i = &raw_pwd[prev..i];
+            let (canonical, _) = deleet(word_i);
+            let display = if !word2rank.contains_key(word_i) && word2rank.contains_key(&canonical)
+            {
+                String::from_utf8_lossy(&canonical).to_string()
+            } else {
+                String::from_utf8_lossy(word_i).to_string()
+            };
+            best_split.push(display);
+            prev = i;
+        }
+        Ok((entropy.into_inner(), best_split))
+    }
+}
+
+fn deleet(word: &[u8]) -> (Vec<u8>, f64) {
+    let mut canonical = Vec::with_capacity(word.len());
+    let mut surcharge = 0f64;
+    for &ch in word {
+        match LEET_SUBSTITUTIONS.iter().find(|(leet, _)| *leet == ch) {
+            Some((_, variants)) => {
+                canonical.push(variants[0]);
+                surcharge += (variants.len() as f64).log2();
+            }
+            None => canonical.push(ch),
+        }
+    }
+    (canonical, surcharge)
 }
 
 pub fn password_mask_cost(pwd: &str) -> f64 {
@@ -59,12 +142,76 @@ pub fn password_mask_cost(pwd: &str) -> f64 {
         .sum()
 }
 
-fn load_vocab(fname: &str) -> BoxResult<HashMap<Vec<u8>, usize>> {
-    let file = File::open(fname)?;
-    let mut reader = BufReader::new(file);
-    let mut buffer: Vec<u8> = Vec::with_capacity(256);
-    let mut word2rank: HashMap<Vec<u8>, usize> = HashMap::new();
+fn char_class_cardinality(ch: u8) -> Option<usize> {
+    if ch.is_ascii_digit() {
+        Some(10)
+    } else if ch.is_ascii_alphabetic() {
+        Some(26)
+    } else {
+        None
+    }
+}
+
+fn sequence_run_len(bytes: &[u8]) -> Option<(usize, usize)> {
+    let class = char_class_cardinality(*bytes.first()?)?;
+    if char_class_cardinality(*bytes.get(1)?) != Some(class) {
+        return None;
+    }
+    let delta = *bytes.get(1)? as i16 - bytes[0] as i16;
+    if delta != 1 && delta != -1 {
+        return None;
+    }
+
+    let mut len = 2;
+    while len < bytes.len()
+        && char_class_cardinality(bytes[len]) == Some(class)
+        && bytes[len] as i16 - bytes[len - 1] as i16 == delta
+    {
+        len += 1;
+    }
 
+    if len >= SEQUENCE_MIN_LEN {
+        Some((len, class))
+    } else {
+        None
+    }
+}
+
+fn repeat_run_len(bytes: &[u8]) -> Option<(usize, usize)> {
+    let class = char_class_cardinality(*bytes.first()?)?;
+
+    let mut best_len = 0;
+    for unit_len in 1..=bytes.len() / 2 {
+        let unit = &bytes[..unit_len];
+        let mut len = unit_len;
+        while len + unit_len <= bytes.len() && &bytes[len..len + unit_len] == unit {
+            len += unit_len;
+        }
+        if len >= unit_len * 2 {
+            best_len = best_len.max(len);
+        }
+    }
+
+    if best_len >= REPEAT_MIN_LEN {
+        Some((best_len, class))
+    } else {
+        None
+    }
+}
+
+fn open_vocab_reader(fname: &str) -> BoxResult<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(fname)?);
+    let is_gzip = fname.ends_with(".gz") || reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+
+    if is_gzip {
+        Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+fn merge_vocab(mut reader: impl BufRead, word2rank: &mut HashMap<Vec<u8>, usize>) -> BoxResult<()> {
+    let mut buffer: Vec<u8> = Vec::with_capacity(256);
     let mut rank = 1;
 
     loop {
@@ -74,36 +221,39 @@ fn load_vocab(fname: &str) -> BoxResult<HashMap<Vec<u8>, usize>> {
                 if buffer.pop().is_some() {
                     let mut word = buffer.to_vec();
                     word.shrink_to_fit();
-                    word2rank.insert(word, rank);
+                    word2rank
+                        .entry(word)
+                        .and_modify(|existing| *existing = (*existing).min(rank))
+                        .or_insert(rank);
                     rank += 1;
                 };
                 buffer.clear();
             }
         }
     }
+    Ok(())
+}
 
+fn fill_missing_single_bytes(word2rank: &mut HashMap<Vec<u8>, usize>) {
     let missing_rank = word2rank.len() + 1;
     for ch in 0..=255u8 {
         word2rank.entry(vec![ch]).or_insert(missing_rank);
     }
-
-    word2rank.shrink_to_fit();
-    Ok(word2rank)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::password_entropy;
-    use crate::password_entropy::password_mask_cost;
+    use crate::password_entropy::{password_mask_cost, EntropyModel};
 
     #[test]
     fn test_compute_password_entropy() {
+        let model = EntropyModel::from_default().unwrap();
         let pwd = "helloworld123!";
-        let res = password_entropy::compute_password_entropy(pwd).unwrap();
+        let res = model.score(pwd).unwrap();
         assert_eq!(
             res,
             (
-                30.823060867312257,
+                13.593391122791736,
                 vec!["helloworld", "123", "!"]
                     .into_iter()
                     .map(String::from)
@@ -114,12 +264,13 @@ mod tests {
 
     #[test]
     fn test_compute_password_entropy_long_password() {
+        let model = EntropyModel::from_default().unwrap();
         let pwd = "helloworld123!helloworld123!helloworld123!";
-        let res = password_entropy::compute_password_entropy(pwd).unwrap();
+        let res = model.score(pwd).unwrap();
         assert_eq!(
             res,
             (
-                92.46918260193678,
+                40.78017336837521,
                 vec![
                     "helloworld",
                     "123",
@@ -140,24 +291,114 @@ mod tests {
 
     #[test]
     fn test_compute_password_entropy_random_password() {
+        let model = EntropyModel::from_default().unwrap();
         let pwd = "E93gtaaE6yF7xDOWv3ww2QE6qD-Wye4mk8O3Vaerem8";
-        let res = password_entropy::compute_password_entropy(pwd).unwrap();
+        let (entropy, split) = model.score(pwd).unwrap();
+        // no dictionary/sequence/repeat/leet match applies anywhere in this
+        // string, so every byte falls back to its own single-byte token
+        assert_eq!(split.len(), pwd.len());
+        assert!(entropy > 0f64);
+    }
+
+    #[test]
+    fn test_compute_password_entropy_ascending_sequence() {
+        let model = EntropyModel::from_default().unwrap();
+        let pwd = "xqz123456";
+        let res = model.score(pwd).unwrap();
         assert_eq!(
             res,
             (
-                206.14950164576396,
-                vec![
-                    "E", "9", "3", "g", "t", "a", "a", "E", "6", "y", "F", "7", "x", "DOW", "v",
-                    "3", "w", "w", "2", "QE", "6", "q", "D-", "W", "y", "e", "4", "m", "k", "8",
-                    "O", "3", "V", "a", "e", "r", "e", "m", "8"
-                ]
+                26.96639217715817,
+                vec!["x", "q", "z", "123456"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            ),
+        );
+    }
+
+    #[test]
+    fn test_compute_password_entropy_descending_sequence() {
+        let model = EntropyModel::from_default().unwrap();
+        let pwd = "xqzfedcba";
+        let res = model.score(pwd).unwrap();
+        assert_eq!(
+            res,
+            (
+                28.344903800411902,
+                vec!["x", "q", "z", "fedcba"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            ),
+        );
+    }
+
+    #[test]
+    fn test_compute_password_entropy_repeat() {
+        let model = EntropyModel::from_default().unwrap();
+        let pwd = "xqzaaaaaa";
+        let res = model.score(pwd).unwrap();
+        assert_eq!(
+            res,
+            (
+                27.344903800411902,
+                vec!["x", "q", "z", "aaaaaa"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            ),
+        );
+    }
+
+    #[test]
+    fn test_compute_password_entropy_leetspeak() {
+        let model = EntropyModel::from_default().unwrap();
+        let pwd = "p@ssw0rd";
+        let res = model.score(pwd).unwrap();
+        assert_eq!(res, (0f64, vec!["password".to_string()]));
+    }
+
+    #[test]
+    fn test_compute_password_entropy_repeated_base_unit() {
+        let model = EntropyModel::from_default().unwrap();
+        let pwd = "xqzabcabcabc";
+        let res = model.score(pwd).unwrap();
+        assert_eq!(
+            res.1,
+            vec!["x", "q", "z", "abcabcabc"]
                 .into_iter()
                 .map(String::from)
-                .collect()
-            ),
+                .collect::<Vec<_>>()
         );
     }
 
+    #[test]
+    fn test_entropy_model_from_files_merges_min_rank() {
+        let model = EntropyModel::from_files(&[
+            &test_resource("vocab-en.txt"),
+            &test_resource("vocab-leaked.txt"),
+        ])
+        .unwrap();
+        // "hunter2" is ranked 1 in vocab-leaked.txt but absent from
+        // vocab-en.txt, so the merged rank must come from the leaked list
+        let (_, split) = model.score("hunter2").unwrap();
+        assert_eq!(split, vec!["hunter2".to_string()]);
+    }
+
+    #[test]
+    fn test_entropy_model_from_files_reads_gzip() {
+        let model = EntropyModel::from_files(&[&test_resource("vocab-en.txt.gz")]).unwrap();
+        let (_, split) = model.score("correcthorse").unwrap();
+        assert_eq!(split, vec!["correcthorse".to_string()]);
+    }
+
+    fn test_resource(fname: &str) -> String {
+        let mut d = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.extend(vec!["test-resources", fname]);
+        d.to_str().unwrap().to_owned()
+    }
+
     #[test]
     fn test_password_mask_cost() {
         let cases: Vec<(&str, f64)> = vec![
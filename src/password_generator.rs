@@ -0,0 +1,128 @@
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::password_entropy::{password_mask_cost, SYMBOLS_SPACE};
+use crate::BoxResult;
+
+pub(crate) const UPPERCASE: &[u8; 26] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub(crate) const LOWERCASE: &[u8; 26] = b"abcdefghijklmnopqrstuvwxyz";
+pub(crate) const DIGITS: &[u8; 10] = b"0123456789";
+
+#[derive(Debug, Clone, Default)]
+pub struct PasswordPolicy {
+    pub length: usize,
+    pub min_upper: usize,
+    pub min_lower: usize,
+    pub min_digit: usize,
+    pub min_symbol: usize,
+    pub min_entropy_bits: Option<f64>,
+}
+
+impl PasswordPolicy {
+    pub fn new(length: usize) -> Self {
+        PasswordPolicy {
+            length,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn generate_password(policy: &PasswordPolicy) -> BoxResult<String> {
+    let required = policy.min_upper + policy.min_lower + policy.min_digit + policy.min_symbol;
+    if required > policy.length {
+        bail!(
+            "policy requires at least {} characters but length is {}",
+            required,
+            policy.length
+        );
+    }
+
+    let mut rng = OsRng;
+    let mut length = policy.length;
+    loop {
+        let candidate = generate_candidate(policy, length, &mut rng);
+        if policy
+            .min_entropy_bits
+            .map_or(true, |min_bits| password_mask_cost(&candidate) >= min_bits)
+        {
+            return Ok(candidate);
+        }
+        length += 1;
+    }
+}
+
+fn generate_candidate(policy: &PasswordPolicy, length: usize, rng: &mut OsRng) -> String {
+    let mut chars: Vec<u8> = Vec::with_capacity(length);
+    chars.extend(random_chars(UPPERCASE, policy.min_upper, rng));
+    chars.extend(random_chars(LOWERCASE, policy.min_lower, rng));
+    chars.extend(random_chars(DIGITS, policy.min_digit, rng));
+    chars.extend(random_chars(SYMBOLS_SPACE, policy.min_symbol, rng));
+
+    // fill the remainder uniformly from the union of all four classes
+    let all: Vec<u8> = UPPERCASE
+        .iter()
+        .chain(LOWERCASE.iter())
+        .chain(DIGITS.iter())
+        .chain(SYMBOLS_SPACE.iter())
+        .copied()
+        .collect();
+    while chars.len() < length {
+        chars.push(all[rng.gen_range(0, all.len())]);
+    }
+
+    chars.shuffle(rng);
+    String::from_utf8(chars).expect("generated password must be ascii")
+}
+
+fn random_chars(alphabet: &[u8], count: usize, rng: &mut OsRng) -> Vec<u8> {
+    (0..count)
+        .map(|_| alphabet[rng.gen_range(0, alphabet.len())])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::password_generator::{generate_password, PasswordPolicy};
+
+    #[test]
+    fn test_generate_password_satisfies_policy() {
+        let policy = PasswordPolicy {
+            length: 12,
+            min_upper: 2,
+            min_lower: 2,
+            min_digit: 2,
+            min_symbol: 2,
+            min_entropy_bits: None,
+        };
+        let pwd = generate_password(&policy).unwrap();
+        assert_eq!(pwd.len(), 12);
+        assert!(pwd.chars().filter(|c| c.is_ascii_uppercase()).count() >= 2);
+        assert!(pwd.chars().filter(|c| c.is_ascii_lowercase()).count() >= 2);
+        assert!(pwd.chars().filter(|c| c.is_ascii_digit()).count() >= 2);
+    }
+
+    #[test]
+    fn test_generate_password_rejects_oversized_requirements() {
+        let policy = PasswordPolicy {
+            length: 4,
+            min_upper: 3,
+            min_lower: 3,
+            min_digit: 0,
+            min_symbol: 0,
+            min_entropy_bits: None,
+        };
+        assert!(generate_password(&policy).is_err());
+    }
+
+    #[test]
+    fn test_generate_password_meets_min_entropy() {
+        let policy = PasswordPolicy {
+            length: 4,
+            min_entropy_bits: Some(40f64),
+            ..PasswordPolicy::new(4)
+        };
+        let pwd = generate_password(&policy).unwrap();
+        assert!(pwd.len() >= 4);
+    }
+}
@@ -1,13 +1,24 @@
 use std::env;
 use std::fs::File;
-use std::io::{stdout, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::io::{self, stdin, stdout, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
 
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
 use crate::create_smartlist::{SmartlistBuilder, SmartlistTokenizer, DEFAULT_VOCAB_SIZE};
 use crate::generators::get_word_generator;
+use crate::grind::{decode, parse_mask_slots, split_ranges, total_combinations, GrindConstraints};
 use crate::helpers::RawFileReader;
+use crate::passphrase::{
+    generate_passphrase, parse_dice_rolls, passphrase_from_dice_rolls, words_for_target_entropy,
+    DICE_ROLLS_PER_WORD,
+};
 use crate::password_entropy::EntropyEstimator;
+use crate::word_policy::{PolicyFilter, WordPolicy};
+use crate::wordlists::OrderedWordlist;
 use crate::{built_info, BoxResult};
 
 const EXAMPLE_USAGE: &str = r#"
@@ -87,10 +98,26 @@ fn parse_args(args: Option<Vec<&str>>) -> ArgMatches<'static> {
     };
 
     // workaround for default subcommand
-    if args.len() >= 2 && !vec!["generate", "entropy", "create", "--help"].contains(&args[1]) {
+    if args.len() >= 2
+        && !vec![
+            "generate",
+            "entropy",
+            "create",
+            "passphrase",
+            "completions",
+            "--help",
+        ]
+        .contains(&args[1])
+    {
         args.insert(1, "generate");
     }
 
+    build_app().get_matches_from(args)
+}
+
+/// builds the clap `App` definition shared by argument parsing and shell
+/// completion generation, so completions can never drift from the real flags
+fn build_app() -> App<'static, 'static> {
     App::new(format!(
         "Cracken v{} - {}",
         built_info::PKG_VERSION,
@@ -194,6 +221,69 @@ available masks are:
             .help("output file to write the wordlist to, defaults to stdout")
             .takes_value(true)
             .required(false),
+    ).arg(
+        Arg::with_name("require-upper")
+            .long("require-upper")
+            .help("require at least N uppercase characters per word (default 1 when given without a value)")
+            .takes_value(true)
+            .min_values(0)
+            .required(false),
+    ).arg(
+        Arg::with_name("require-lower")
+            .long("require-lower")
+            .help("require at least N lowercase characters per word (default 1 when given without a value)")
+            .takes_value(true)
+            .min_values(0)
+            .required(false),
+    ).arg(
+        Arg::with_name("require-digit")
+            .long("require-digit")
+            .help("require at least N digit characters per word (default 1 when given without a value)")
+            .takes_value(true)
+            .min_values(0)
+            .required(false),
+    ).arg(
+        Arg::with_name("require-symbol")
+            .long("require-symbol")
+            .help("require at least N symbol characters per word (default 1 when given without a value)")
+            .takes_value(true)
+            .min_values(0)
+            .required(false),
+    ).arg(
+        Arg::with_name("min-classes")
+            .long("min-classes")
+            .help("require at least N of the four character classes (upper/lower/digit/symbol) to be present per word")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("grind-starts")
+            .long("grind-starts")
+            .help("grind mode: only emit words starting with one of these strings, can be specified multiple times")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1),
+    ).arg(
+        Arg::with_name("grind-ends")
+            .long("grind-ends")
+            .help("grind mode: only emit words ending with one of these strings, can be specified multiple times")
+            .takes_value(true)
+            .required(false)
+            .multiple(true)
+            .number_of_values(1),
+    ).arg(
+        Arg::with_name("grind-count")
+            .long("grind-count")
+            .help("grind mode: stop all worker threads once this many matches have been found")
+            .takes_value(true)
+            .required(false),
+    ).arg(
+        Arg::with_name("grind-threads")
+            .long("grind-threads")
+            .help("grind mode: number of worker threads to split the keyspace across")
+            .takes_value(true)
+            .required(false)
+            .default_value("4"),
     )).subcommand(SubCommand::with_name("entropy")
         .about(r#"
 Computes the estimated entropy of password or password file.
@@ -314,8 +404,61 @@ There are two types of keyspace size estimations:
             .takes_value(true)
             .required(false)
         )
+    ).subcommand(SubCommand::with_name("passphrase")
+        .about("Generates diceware-style passphrases from a wordlist")
+        .arg(
+        Arg::with_name("words")
+            .short("n")
+            .long("words")
+            .help("number of words to draw per passphrase")
+            .takes_value(true)
+            .required_unless("target-entropy"),
+        ).arg(
+        Arg::with_name("target-entropy")
+            .long("target-entropy")
+            .help("instead of --words, draw the minimum number of words whose combined entropy reaches this many bits, and print the achieved entropy")
+            .takes_value(true)
+            .required_unless("words")
+            .conflicts_with("words"),
+        ).arg(
+        Arg::with_name("wordlist")
+            .short("w")
+            .long("wordlist")
+            .help("wordlist file to draw words from, a newline (0xA) separated text file")
+            .takes_value(true)
+            .required(true),
+        ).arg(
+        Arg::with_name("separator")
+            .long("separator")
+            .help("separator to join words with")
+            .takes_value(true)
+            .default_value(" "),
+        ).arg(
+        Arg::with_name("count")
+            .short("c")
+            .long("count")
+            .help("number of passphrases to generate")
+            .takes_value(true)
+            .required(false),
+        ).arg(
+        Arg::with_name("dicerolls")
+            .long("dicerolls")
+            .help(
+                "reads physical d6 dice rolls from stdin instead of a CSPRNG - 5 rolls (1-6) per word",
+            )
+            .takes_value(false)
+            .required(false),
+        )
+    ).subcommand(SubCommand::with_name("completions")
+        .about("Generates a shell completion script for the given shell")
+        .arg(
+        Arg::with_name("shell")
+            .help("the shell to generate a completion script for")
+            .takes_value(true)
+            .possible_values(&["bash", "zsh", "fish", "powershell", "elvish"])
+            .required(true),
+        )
     )
-    .get_matches_from(args)
 }
 
 /// helper for handling cast and optional values at same time, exiting on error
@@ -347,6 +490,8 @@ pub fn run(args: Option<Vec<&str>>) -> BoxResult<()> {
         ("generate", Some(matches)) => run_wordlist_generator(matches),
         ("create", Some(matches)) => run_create_smartlist(matches),
         ("entropy", Some(matches)) => run_entropy_estimator(matches),
+        ("passphrase", Some(matches)) => run_passphrase(matches),
+        ("completions", Some(matches)) => run_completions(matches),
         (_, None) => bail!("invalid command"),
         _ => unreachable!("oopsie, subcommand is required"),
     }
@@ -366,6 +511,7 @@ pub fn run_wordlist_generator(args: &ArgMatches) -> BoxResult<()> {
     let minlen = optional_value_t_or_exit!(args, "min-length", usize);
     let maxlen = optional_value_t_or_exit!(args, "max-length", usize);
     let outfile = args.value_of("output-file");
+    let policy = word_policy_from_args(args)?;
 
     // create output file
     let mut out: Box<dyn Write> = match outfile {
@@ -386,30 +532,175 @@ pub fn run_wordlist_generator(args: &ArgMatches) -> BoxResult<()> {
         .map(|x| x.collect())
         .unwrap_or_else(Vec::new);
 
+    let grind_active = args.is_present("grind-starts")
+        || args.is_present("grind-ends")
+        || args.is_present("grind-count");
+
     for mask in masks {
+        if grind_active {
+            if args.is_present("stats") {
+                bail!("--stats is not supported together with grind mode");
+            }
+            let constraints = GrindConstraints {
+                starts: args
+                    .values_of("grind-starts")
+                    .map(|x| x.map(String::from).collect())
+                    .unwrap_or_default(),
+                ends: args
+                    .values_of("grind-ends")
+                    .map(|x| x.map(String::from).collect())
+                    .unwrap_or_default(),
+            };
+            let grind_count = optional_value_t_or_exit!(args, "grind-count", u64);
+            let grind_threads = optional_value_t_or_exit!(args, "grind-threads", usize).unwrap();
+
+            run_grind(
+                &mask,
+                minlen,
+                maxlen,
+                &custom_charsets,
+                &wordlists,
+                constraints,
+                grind_threads,
+                grind_count,
+                &mut out,
+            )?;
+            continue;
+        }
+
         // create output file
         let word_generator =
             get_word_generator(&mask, minlen, maxlen, &custom_charsets, &wordlists)?;
         if args.is_present("stats") {
-            let combs = word_generator.combinations();
-            println!("{}", combs);
+            if policy.is_noop() {
+                let combs = word_generator.combinations();
+                println!("{}", combs);
+            } else {
+                // mask combinatorics alone can't account for the filter, so
+                // run the generator through a counting-only sink instead
+                let mut counter = PolicyFilter::new(io::sink(), policy);
+                word_generator.gen(&mut counter)?;
+                println!("{}", counter.matched_count());
+            }
             return Ok(());
         }
 
-        match word_generator.gen(&mut out) {
-            Ok(_) => {}
-            Err(e) => {
-                match e.kind() {
-                    // ignore broken pipe, (e.g. happens when using head)
-                    ErrorKind::BrokenPipe => return Ok(()),
-                    _ => bail!("error occurred writing to out: {}", e),
+        let gen_result = if policy.is_noop() {
+            word_generator.gen(&mut out)
+        } else {
+            word_generator.gen(&mut PolicyFilter::new(&mut out, policy))
+        };
+
+        if let Err(e) = gen_result {
+            match e.kind() {
+                // ignore broken pipe, (e.g. happens when using head)
+                ErrorKind::BrokenPipe => return Ok(()),
+                _ => bail!("error occurred writing to out: {}", e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// splits the mask's keyspace across `threads` workers, each seeking
+/// directly into its own `[start, end)` slice via `decode`
+#[allow(clippy::too_many_arguments)]
+fn run_grind(
+    mask: &str,
+    minlen: Option<usize>,
+    maxlen: Option<usize>,
+    custom_charsets: &[&str],
+    wordlists: &[&str],
+    constraints: GrindConstraints,
+    threads: usize,
+    limit: Option<u64>,
+    out: &mut dyn Write,
+) -> BoxResult<()> {
+    if minlen.is_some() || maxlen.is_some() {
+        bail!("grind mode doesn't support --min-length/--max-length (variable-length masks)");
+    }
+    if !wordlists.is_empty() {
+        bail!("grind mode doesn't support --wordlist");
+    }
+
+    let slots = parse_mask_slots(mask, custom_charsets)?;
+    let combinations = total_combinations(&slots)?;
+    let ranges = split_ranges(combinations, threads as u64);
+    let found = Arc::new(AtomicU64::new(0));
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+
+    let handles: Vec<_> = ranges
+        .into_iter()
+        .map(|range| {
+            let slots = slots.clone();
+            let constraints = constraints.clone();
+            let tx = tx.clone();
+            let found = Arc::clone(&found);
+
+            thread::spawn(move || {
+                for index in range.start..range.end {
+                    if let Some(limit) = limit {
+                        if found.load(Ordering::Relaxed) >= limit {
+                            break;
+                        }
+                    }
+                    let word = decode(&slots, index);
+                    if constraints.matches(&word) && tx.send(word).is_ok() {
+                        found.fetch_add(1, Ordering::Relaxed);
+                    }
                 }
+            })
+        })
+        .collect();
+
+    // drop our own sender so the receiver loop below ends once every
+    // worker's cloned sender has been dropped
+    drop(tx);
+
+    for word in rx {
+        if let Err(e) = writeln!(out, "{}", String::from_utf8_lossy(&word)) {
+            match e.kind() {
+                ErrorKind::BrokenPipe => break,
+                _ => bail!("error occurred writing to out: {}", e),
             }
         }
     }
+
+    for handle in handles {
+        if handle.join().is_err() {
+            bail!("grind worker thread panicked");
+        }
+    }
+
     Ok(())
 }
 
+/// builds a `WordPolicy` from the `--require-*`/`--min-classes` flags:
+/// each `--require-*` flag defaults to a minimum of 1 when given without an
+/// explicit value
+fn word_policy_from_args(args: &ArgMatches) -> BoxResult<WordPolicy> {
+    Ok(WordPolicy {
+        min_upper: required_count(args, "require-upper")?,
+        min_lower: required_count(args, "require-lower")?,
+        min_digit: required_count(args, "require-digit")?,
+        min_symbol: required_count(args, "require-symbol")?,
+        min_classes: optional_value_t_or_exit!(args, "min-classes", usize).unwrap_or(0),
+    })
+}
+
+fn required_count(args: &ArgMatches, name: &str) -> BoxResult<usize> {
+    if args.occurrences_of(name) == 0 {
+        return Ok(0);
+    }
+    match args.value_of(name) {
+        Some(v) => match v.parse() {
+            Ok(n) => Ok(n),
+            Err(_) => bail!("invalid value for --{}: {}", name, v),
+        },
+        None => Ok(1),
+    }
+}
+
 pub fn run_entropy_estimator(args: &ArgMatches) -> BoxResult<()> {
     let smartlist_files: Vec<&str> = args.values_of("smartlist").map(|x| x.collect()).unwrap();
     let est = EntropyEstimator::from_files(smartlist_files.as_ref())?;
@@ -528,6 +819,92 @@ pub fn run_create_smartlist(args: &ArgMatches) -> BoxResult<()> {
     Ok(())
 }
 
+pub fn run_passphrase(args: &ArgMatches) -> BoxResult<()> {
+    let wordlist_fname = args.value_of("wordlist").unwrap();
+    let separator = args.value_of("separator").unwrap_or(" ");
+    let count = optional_value_t_or_exit!(args, "count", usize).unwrap_or(1);
+
+    let wordlist = OrderedWordlist::from_file(wordlist_fname)?;
+
+    let target_entropy = optional_value_t_or_exit!(args, "target-entropy", f64);
+    let word_count = match target_entropy {
+        Some(target_bits) => {
+            if wordlist.len() < 2 {
+                bail!(
+                    "wordlist must contain at least 2 words to target an entropy bound, got {}",
+                    wordlist.len()
+                );
+            }
+            words_for_target_entropy(wordlist.len(), target_bits)
+        }
+        None => optional_value_t_or_exit!(args, "words", usize).unwrap(),
+    };
+    if word_count == 0 {
+        bail!("--words must be at least 1, got 0");
+    }
+
+    let mut stdout = stdout();
+
+    let passphrases = if args.is_present("dicerolls") {
+        let mut input = String::new();
+        stdin().read_to_string(&mut input)?;
+        let rolls = parse_dice_rolls(&input)?;
+        let rolls_per_passphrase = word_count * DICE_ROLLS_PER_WORD;
+        if rolls.is_empty() || rolls.len() % rolls_per_passphrase != 0 {
+            bail!(
+                "dice rolls must come in groups of {} ({} words * {} rolls/word), got {}",
+                rolls_per_passphrase,
+                word_count,
+                DICE_ROLLS_PER_WORD,
+                rolls.len()
+            );
+        }
+        rolls
+            .chunks(rolls_per_passphrase)
+            .map(|group| passphrase_from_dice_rolls(&wordlist, group))
+            .collect::<BoxResult<Vec<_>>>()?
+    } else {
+        (0..count)
+            .map(|_| generate_passphrase(&wordlist, word_count))
+            .collect()
+    };
+
+    for passphrase in passphrases {
+        let line = if target_entropy.is_some() {
+            format!(
+                "{} (entropy: {:.2} bits)",
+                passphrase.join(separator),
+                passphrase.entropy_bits
+            )
+        } else {
+            passphrase.join(separator)
+        };
+
+        if let Err(e) = writeln!(&mut stdout, "{}", line) {
+            match e.kind() {
+                // ignore broken pipe, (e.g. happens when using head)
+                ErrorKind::BrokenPipe => return Ok(()),
+                _ => bail!("error occurred writing to out: {}", e),
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run_completions(args: &ArgMatches) -> BoxResult<()> {
+    let shell = match args.value_of("shell").unwrap() {
+        "bash" => clap::Shell::Bash,
+        "zsh" => clap::Shell::Zsh,
+        "fish" => clap::Shell::Fish,
+        "powershell" => clap::Shell::PowerShell,
+        "elvish" => clap::Shell::Elvish,
+        other => unreachable!("invalid shell {}", other),
+    };
+
+    build_app().gen_completions_to(built_info::PKG_NAME, shell, &mut stdout());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{runner, test_util};
@@ -552,12 +929,125 @@ mod tests {
         assert!(runner::run(args).is_ok());
     }
 
+    #[test]
+    fn test_run_passphrase_smoke() {
+        let wordlist_fname = test_util::wordlist_fname("vocab-en.txt");
+        let args = Some(vec![
+            "cracken",
+            "passphrase",
+            "-n",
+            "3",
+            "-w",
+            wordlist_fname.to_str().unwrap(),
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_passphrase_target_entropy() {
+        let wordlist_fname = test_util::wordlist_fname("vocab-en.txt");
+        let args = Some(vec![
+            "cracken",
+            "passphrase",
+            "--target-entropy",
+            "40",
+            "-w",
+            wordlist_fname.to_str().unwrap(),
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_completions_smoke() {
+        for shell in &["bash", "zsh", "fish", "powershell", "elvish"] {
+            let args = Some(vec!["cracken", "completions", shell]);
+            assert!(runner::run(args).is_ok());
+        }
+    }
+
     #[test]
     fn test_run_dev_null() {
         let args = Some(vec!["cracken", "-o", "/dev/null", "?d"]);
         assert!(runner::run(args).is_ok());
     }
 
+    #[test]
+    fn test_run_generate_with_word_policy() {
+        let args = Some(vec![
+            "cracken",
+            "generate",
+            "-m",
+            "4",
+            "-x",
+            "4",
+            "--require-upper",
+            "--require-digit",
+            "1",
+            "?l?l?u?d",
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_generate_stats_with_word_policy() {
+        let args = Some(vec![
+            "cracken",
+            "-s",
+            "--min-classes",
+            "2",
+            "?l?l?u?d",
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_generate_grind_mode() {
+        let args = Some(vec![
+            "cracken",
+            "generate",
+            "-o",
+            "/dev/null",
+            "--grind-starts",
+            "aa",
+            "--grind-threads",
+            "2",
+            "?l?l?l",
+        ]);
+        assert!(runner::run(args).is_ok());
+    }
+
+    #[test]
+    fn test_run_generate_grind_mode_rejects_variable_length() {
+        let args = Some(vec![
+            "cracken",
+            "generate",
+            "-o",
+            "/dev/null",
+            "-m",
+            "2",
+            "-x",
+            "3",
+            "--grind-starts",
+            "aa",
+            "?l?l?l",
+        ]);
+        assert!(runner::run(args).is_err());
+    }
+
+    #[test]
+    fn test_run_passphrase_rejects_zero_words() {
+        let wordlist_fname = test_util::wordlist_fname("vocab-en.txt");
+        let args = Some(vec![
+            "cracken",
+            "passphrase",
+            "-n",
+            "0",
+            "-w",
+            wordlist_fname.to_str().unwrap(),
+        ]);
+        assert!(runner::run(args).is_err());
+    }
+
     #[test]
     fn test_run_custom_charset() {
         let args = Some(vec!["cracken", "-c=abcdef0123456789", "?1"]);
@@ -0,0 +1,150 @@
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WordPolicy {
+    pub min_upper: usize,
+    pub min_lower: usize,
+    pub min_digit: usize,
+    pub min_symbol: usize,
+    pub min_classes: usize,
+}
+
+impl WordPolicy {
+    pub fn is_noop(&self) -> bool {
+        *self == WordPolicy::default()
+    }
+
+    pub fn matches(&self, word: &[u8]) -> bool {
+        let (mut upper, mut lower, mut digit, mut symbol) = (0usize, 0usize, 0usize, 0usize);
+        for &b in word {
+            if b.is_ascii_uppercase() {
+                upper += 1;
+            } else if b.is_ascii_lowercase() {
+                lower += 1;
+            } else if b.is_ascii_digit() {
+                digit += 1;
+            } else {
+                symbol += 1;
+            }
+        }
+
+        if upper < self.min_upper
+            || lower < self.min_lower
+            || digit < self.min_digit
+            || symbol < self.min_symbol
+        {
+            return false;
+        }
+
+        let classes_present = [upper, lower, digit, symbol]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count();
+        classes_present >= self.min_classes
+    }
+}
+
+/// wraps a `Write` sink and drops every line failing `policy`
+pub struct PolicyFilter<W: Write> {
+    inner: W,
+    policy: WordPolicy,
+    buf: Vec<u8>,
+    matched: usize,
+}
+
+impl<W: Write> PolicyFilter<W> {
+    pub fn new(inner: W, policy: WordPolicy) -> Self {
+        PolicyFilter {
+            inner,
+            policy,
+            buf: Vec::new(),
+            matched: 0,
+        }
+    }
+
+    pub fn matched_count(&self) -> usize {
+        self.matched
+    }
+
+    fn emit_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.policy.matches(line) {
+            self.matched += 1;
+            self.inner.write_all(line)?;
+            self.inner.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for PolicyFilter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            self.emit_line(&line[..line.len() - 1])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            let line = std::mem::take(&mut self.buf);
+            self.emit_line(&line)?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::word_policy::{PolicyFilter, WordPolicy};
+    use std::io::Write;
+
+    #[test]
+    fn test_word_policy_noop() {
+        assert!(WordPolicy::default().is_noop());
+        assert!(!WordPolicy {
+            min_upper: 1,
+            ..WordPolicy::default()
+        }
+        .is_noop());
+    }
+
+    #[test]
+    fn test_word_policy_matches_per_class_minimums() {
+        let policy = WordPolicy {
+            min_upper: 1,
+            min_digit: 1,
+            ..WordPolicy::default()
+        };
+        assert!(policy.matches(b"Abc123"));
+        assert!(!policy.matches(b"abc123"));
+        assert!(!policy.matches(b"ABCabc"));
+    }
+
+    #[test]
+    fn test_word_policy_min_classes() {
+        let policy = WordPolicy {
+            min_classes: 3,
+            ..WordPolicy::default()
+        };
+        assert!(policy.matches(b"Abc123"));
+        assert!(!policy.matches(b"abcdef"));
+    }
+
+    #[test]
+    fn test_policy_filter_drops_non_matching_lines() {
+        let policy = WordPolicy {
+            min_digit: 1,
+            ..WordPolicy::default()
+        };
+        let mut out = Vec::new();
+        {
+            let mut filter = PolicyFilter::new(&mut out, policy);
+            filter.write_all(b"abc\nabc1\ndef2\n").unwrap();
+            filter.flush().unwrap();
+            assert_eq!(filter.matched_count(), 2);
+        }
+        assert_eq!(out, b"abc1\ndef2\n");
+    }
+}
@@ -2,6 +2,52 @@ use std::fs::File;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::ops::Index;
 
+fn read_lines(fname: &str) -> std::io::Result<Vec<Vec<u8>>> {
+    let numlines = {
+        let fp = BufReader::new(File::open(fname)?);
+        fp.split(b'\n').count()
+    };
+
+    let fp = BufReader::new(File::open(fname)?);
+    let mut words = Vec::with_capacity(numlines + 1);
+
+    fp.split(b'\n')
+        .map(|word| {
+            let mut word = word?;
+            if !word.is_empty() {
+                if *word.last().unwrap() == b'\n' {
+                    word.pop();
+                }
+                word.shrink_to_fit();
+                words.push(word);
+            }
+            Ok(())
+        })
+        .collect::<Result<(), std::io::Error>>()?;
+
+    //        loop {
+    //            let mut word = vec![];
+    //
+    //            match fp.read_until(b'\n', &mut word) {
+    //                Ok(0) => break,
+    //                Err(_) => break,
+    //                Ok(_) => {},
+    //            }
+    //
+    //            if word.is_empty() {
+    //                continue
+    //            }
+    //
+    //            if *word.last().unwrap() == b'\n' {
+    //                word.pop();
+    //            }
+    //            word.shrink_to_fit();
+    //            words.push(word);
+    //        }
+    words.shrink_to_fit();
+    Ok(words)
+}
+
 #[derive(Debug)]
 pub struct Wordlist {
     words: Vec<Vec<u8>>,
@@ -9,48 +55,7 @@ pub struct Wordlist {
 
 impl Wordlist {
     pub fn from_file(fname: &str) -> std::io::Result<Wordlist> {
-        let numlines = {
-            let fp = BufReader::new(File::open(fname)?);
-            fp.split(b'\n').count()
-        };
-
-        let fp = BufReader::new(File::open(fname)?);
-        let mut words = Vec::with_capacity(numlines + 1);
-
-        fp.split(b'\n')
-            .map(|word| {
-                let mut word = word?;
-                if !word.is_empty() {
-                    if *word.last().unwrap() == b'\n' {
-                        word.pop();
-                    }
-                    word.shrink_to_fit();
-                    words.push(word);
-                }
-                Ok(())
-            })
-            .collect::<Result<(), std::io::Error>>()?;
-
-        //        loop {
-        //            let mut word = vec![];
-        //
-        //            match fp.read_until(b'\n', &mut word) {
-        //                Ok(0) => break,
-        //                Err(_) => break,
-        //                Ok(_) => {},
-        //            }
-        //
-        //            if word.is_empty() {
-        //                continue
-        //            }
-        //
-        //            if *word.last().unwrap() == b'\n' {
-        //                word.pop();
-        //            }
-        //            word.shrink_to_fit();
-        //            words.push(word);
-        //        }
-        words.shrink_to_fit();
+        let mut words = read_lines(fname)?;
         words.sort_unstable_by(|a, b| a.len().cmp(&b.len()));
         Ok(Wordlist { words })
     }
@@ -69,9 +74,38 @@ impl Index<usize> for Wordlist {
     }
 }
 
+/// same on-disk format as `Wordlist`, but keeps file order instead of
+/// sorting by length - needed wherever a word's index must stay stable and
+/// reproducible, e.g. mapping dice rolls to diceware words
+#[derive(Debug)]
+pub struct OrderedWordlist {
+    words: Vec<Vec<u8>>,
+}
+
+impl OrderedWordlist {
+    pub fn from_file(fname: &str) -> std::io::Result<OrderedWordlist> {
+        Ok(OrderedWordlist {
+            words: read_lines(fname)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+}
+
+impl Index<usize> for OrderedWordlist {
+    type Output = Vec<u8>;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.words[index]
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::wordlists::Wordlist;
+    use crate::wordlists::{OrderedWordlist, Wordlist};
     use std::path;
 
     #[test]
@@ -88,6 +122,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ordered_wordlist_preserves_file_order() {
+        let ordered = OrderedWordlist::from_file(&wordlist_fname("wordlist1.txt")).unwrap();
+        let sorted = Wordlist::from_file(&wordlist_fname("wordlist1.txt")).unwrap();
+        assert_eq!(ordered.len(), sorted.len());
+        // same multiset of words, but not necessarily the same order as the
+        // length-sorted Wordlist
+        let mut ordered_words: Vec<_> = (0..ordered.len()).map(|i| ordered[i].clone()).collect();
+        let mut sorted_words: Vec<_> = (0..sorted.len()).map(|i| sorted[i].clone()).collect();
+        ordered_words.sort();
+        sorted_words.sort();
+        assert_eq!(ordered_words, sorted_words);
+    }
+
     fn wordlist_fname(fname: &str) -> String {
         let mut d = path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.extend(vec!["test-resources", fname]);